@@ -2,24 +2,40 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{quote, ToTokens};
-use syn::{parse_macro_input, ItemFn, Meta};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Expr, ItemFn, Meta, MetaNameValue, Token};
 
-/// Macro to mark an `rdroidtest` test function.  Can take one optional argument, an expression that
-/// evaluates to a `Vec` of parameter (name, value) pairs.
+/// Macro to mark an `rdroidtest` test function.  Can take zero or more arguments, each an
+/// expression that evaluates to a `Vec` of parameter (name, value) pairs. With more than one
+/// argument, the test is run over the Cartesian product of all of them, e.g.
+/// `#[rdroidtest(sizes(), alignments())]` on `fn test(size: u32, alignment: u32)`.
 ///
-/// Also detects `#[ignore]` and `#[ignore_if(<expr>)]` attributes on the test function.
+/// Also detects `#[ignore]`, `#[ignore_if(<expr>)]` (optionally `#[ignore_if(<expr>, <reason>)]`
+/// to record why, for the ignored-test report), `#[should_panic]` and
+/// `#[should_panic(expected = "...")]` attributes on the test function. The two-argument
+/// `ignore_if` form only works on non-parameterized tests; on a parameterized test the reason is
+/// given by having the `ignore_if` closure return a `(bool, <reason>)` pair.
 #[proc_macro_attribute]
 pub fn rdroidtest(args: TokenStream, item: TokenStream) -> TokenStream {
     // Only accept code that parses as a function definition.
     let item = parse_macro_input!(item as ItemFn);
     let fn_name = &item.sig.ident;
 
-    // If the attribute has any arguments, they are expected to be a parameter generator expression.
-    let param_gen: Option<TokenStream2> = if args.is_empty() { None } else { Some(args.into()) };
+    // If the attribute has any arguments, they are expected to be one or more parameter
+    // generator expressions, one per axis of the test matrix.
+    let param_gens: Vec<Expr> = if args.is_empty() {
+        Vec::new()
+    } else {
+        let parser = Punctuated::<Expr, Token![,]>::parse_terminated;
+        parser.parse(args).expect("expected a comma-separated list of parameter generator expressions").into_iter().collect()
+    };
 
-    // Look for `#[ignore]` and `#[ignore_if(<expr>)]` attributes on the wrapped item.
+    // Look for `#[ignore]`, `#[ignore_if(<expr>)]` and `#[should_panic(...)]` attributes on the
+    // wrapped item.
     let mut ignore_if: Option<TokenStream2> = None;
     let mut ignored = false;
+    let mut should_panic: Option<TokenStream2> = None;
     for attr in &item.attrs {
         match &attr.meta {
             Meta::Path(path) if path.to_token_stream().to_string().as_str() == "ignore" => {
@@ -27,28 +43,65 @@ pub fn rdroidtest(args: TokenStream, item: TokenStream) -> TokenStream {
                 ignored = true;
             }
             Meta::List(list) if list.path.to_token_stream().to_string().as_str() == "ignore_if" => {
-                // `#[ignore_if(<expr>)]` attribute.
-                ignore_if = Some(list.tokens.clone());
+                // `#[ignore_if(<expr>)]`, or `#[ignore_if(<expr>, <reason>)]` to attach a
+                // human-readable reason to the ignored-test report.
+                let parser = Punctuated::<Expr, Token![,]>::parse_terminated;
+                let exprs: Vec<Expr> = parser
+                    .parse2(list.tokens.clone())
+                    .expect("expected `ignore_if(<expr>)` or `ignore_if(<expr>, <reason>)`")
+                    .into_iter()
+                    .collect();
+                ignore_if = Some(match exprs.as_slice() {
+                    [cond] => quote! { #cond },
+                    [cond, reason] => quote! { (#cond, #reason) },
+                    _ => panic!("expected `ignore_if(<expr>)` or `ignore_if(<expr>, <reason>)`"),
+                });
+            }
+            Meta::Path(path) if path.to_token_stream().to_string().as_str() == "should_panic" => {
+                // `#[should_panic]` attribute, with no expected message.
+                should_panic = Some(quote! { ::std::option::Option::None });
+            }
+            Meta::List(list) if list.path.to_token_stream().to_string().as_str() == "should_panic" => {
+                // `#[should_panic(expected = "...")]` attribute.
+                let name_value: MetaNameValue = syn::parse2(list.tokens.clone())
+                    .expect("expected `should_panic(expected = \"...\")`");
+                assert!(
+                    name_value.path.to_token_stream().to_string().as_str() == "expected",
+                    "expected `should_panic(expected = \"...\")`"
+                );
+                let message = &name_value.value;
+                should_panic = Some(quote! { ::std::option::Option::Some(#message) });
             }
             _ => {}
         }
     }
     if ignored {
         // `#[ignore]` trumps any specified `#[ignore_if]`.
-        ignore_if = Some(if param_gen.is_some() {
-            // `ignore_if` needs to be something invoked with a single parameter.
-            quote! { |_p| true }.into_iter().collect()
-        } else {
+        ignore_if = Some(if param_gens.is_empty() {
             quote! { true }.into_iter().collect()
+        } else {
+            // `ignore_if` needs to be something invoked with a single parameter (the value, or
+            // the tuple of values for a parameter matrix).
+            quote! { |_p| true }.into_iter().collect()
         });
     }
 
-    // Build up an invocation of the appropriate `rdroidtest` declarative macro.
-    let invocation = match (param_gen, ignore_if) {
-        (Some(pg), Some(ii)) => quote! { ::rdroidtest::ptest!( #fn_name, #pg, ignore_if: #ii ); },
-        (Some(pg), None) => quote! { ::rdroidtest::ptest!( #fn_name, #pg ); },
-        (None, Some(ii)) => quote! { ::rdroidtest::test!( #fn_name, ignore_if: #ii ); },
-        (None, None) => quote! { ::rdroidtest::test!( #fn_name ); },
+    // Build up the optional trailing `ignore_if:`/`should_panic:` clauses, then pick the
+    // declarative macro matching the number of parameter generators.
+    let mut tail = TokenStream2::new();
+    if let Some(ii) = &ignore_if {
+        tail.extend(quote! { , ignore_if: #ii });
+    }
+    if let Some(sp) = &should_panic {
+        tail.extend(quote! { , should_panic: #sp });
+    }
+    let invocation = match param_gens.len() {
+        0 => quote! { ::rdroidtest::test!( #fn_name #tail ); },
+        1 => {
+            let pg = &param_gens[0];
+            quote! { ::rdroidtest::ptest!( #fn_name, #pg #tail ); }
+        }
+        _ => quote! { ::rdroidtest::ptest!( #fn_name, [#(#param_gens),*] #tail ); },
     };
 
     let mut stream = TokenStream2::new();
@@ -63,3 +116,19 @@ pub fn rdroidtest(args: TokenStream, item: TokenStream) -> TokenStream {
 pub fn ignore_if(_args: TokenStream, item: TokenStream) -> TokenStream {
     item
 }
+
+/// Macro to mark a setup/teardown fixture function, registering it via [`rdroidtest::fixture!`].
+/// The wrapped function takes no arguments and returns a `Box<dyn FnOnce() + Send>` teardown,
+/// which runs after every test in (or below) the fixture's module.
+#[proc_macro_attribute]
+pub fn rdroidtest_fixture(args: TokenStream, item: TokenStream) -> TokenStream {
+    assert!(args.is_empty(), "#[rdroidtest_fixture] does not take any arguments");
+    let item = parse_macro_input!(item as ItemFn);
+    let fn_name = &item.sig.ident;
+    let invocation = quote! { ::rdroidtest::fixture!( #fn_name ); };
+
+    let mut stream = TokenStream2::new();
+    stream.extend([invocation]);
+    stream.extend(item.into_token_stream());
+    stream.into_token_stream().into()
+}
@@ -0,0 +1,71 @@
+//! Alternative test collection backend built on the unstable `custom_test_frameworks` language
+//! feature, for use where `linkme`'s linker-section registration isn't available, or where tests
+//! need to be attached to items the `test!`/`ptest!` macros can't reach (since those require a
+//! nameable module-level `fn`, and `custom_test_frameworks` tests can live anywhere a `const`
+//! can).
+//!
+//! A test crate opts in with:
+//!
+//! ```ignore
+//! #![feature(custom_test_frameworks)]
+//! #![test_runner(rdroidtest::custom_test_framework::run_all)]
+//! ```
+//!
+//! and declares tests as `#[test_case]` statics of type [`TestDescAndFn`], instead of with the
+//! `test!`/`ptest!` macros (which remain available, unchanged, on the `linkme` backend).
+
+use libtest_mimic::{Arguments, Failed, Trial};
+
+use crate::runner::Shard;
+
+/// Descriptor for a single test registered via `#[test_case]`, playing the same role as the
+/// standard library's (unstable, internal) `test::TestDescAndFn`: a name, an `ignore_if` check,
+/// and the test function itself.
+pub struct TestDescAndFn {
+    /// The test's fully-qualified name, as reported by the test harness.
+    pub name: &'static str,
+    /// Returns whether the test should be skipped, and an optional human-readable reason --
+    /// the same `(bool, Option<&str>)` shape [`crate::IntoIgnoreResult`] normalizes `ignore_if`
+    /// expressions into. Evaluated at run time, so it can depend on runtime state the same way
+    /// the `linkme` backend's `ignore_if:` clause can.
+    pub ignore_if: fn() -> (bool, Option<&'static str>),
+    /// The test body.
+    pub func: fn(),
+}
+
+/// `#[test_runner]`-compatible entry point: adapts the `#[test_case]` descriptors the compiler
+/// collects into `libtest_mimic::Trial`s and runs them, applying the same `ignore_if`/`#[ignore]`
+/// semantics and deterministic `--shard-index`/`--shard-count` sharding as `runner::main`.
+///
+/// Fixtures (`fixture!`/`#[rdroidtest_fixture]`) are a `linkme`-backend-only feature; this backend
+/// has no module path to match them against, so it does not run them.
+pub fn run_all(tests: &[&TestDescAndFn]) {
+    let raw_args: Vec<String> = std::env::args().collect();
+    let (shard, remaining_args) = Shard::extract_from_args(&raw_args);
+    let args = Arguments::from_iter(remaining_args);
+
+    let mut trials: Vec<Trial> = tests
+        .iter()
+        .map(|test| {
+            let (ignored, reason) = (test.ignore_if)();
+            if ignored {
+                if let Some(reason) = reason {
+                    crate::runner::record_ignore_reason(test.name, reason);
+                }
+            }
+            let func = test.func;
+            Trial::test(test.name, move || run_test(func)).with_ignored_flag(ignored)
+        })
+        .collect();
+    if let Some(shard) = shard {
+        trials = shard.select(trials);
+    }
+    crate::runner::print_ignore_reasons(&trials);
+    libtest_mimic::run(&args, trials).exit();
+}
+
+/// Runs a single `#[test_case]` function, converting a panic into a `Failed` the same way
+/// `runner::run` does for the `linkme` backend.
+fn run_test(func: fn()) -> Result<(), Failed> {
+    std::panic::catch_unwind(func).map_err(|e| Failed::from(crate::runner::panic_message(&*e)))
+}
@@ -1,9 +1,10 @@
 //! Test harness which supports ignoring tests at runtime.
 
+pub mod custom_test_framework;
 pub mod runner;
 
 // Re-export the attribute macros.
-pub use rdroidtest_macro::{ignore_if, rdroidtest};
+pub use rdroidtest_macro::{ignore_if, rdroidtest, rdroidtest_fixture};
 
 #[doc(hidden)]
 pub use libtest_mimic as _libtest_mimic;
@@ -12,6 +13,26 @@ pub use linkme as _linkme;
 #[doc(hidden)]
 pub use paste as _paste;
 
+/// Implementation detail: normalizes the return value of an `ignore_if` expression or closure --
+/// either a plain `bool`, or a `(bool, &str)` pair carrying a human-readable reason -- into a
+/// uniform `(bool, Option<&str>)`.
+#[doc(hidden)]
+pub trait IntoIgnoreResult {
+    fn into_ignore_result(self) -> (bool, Option<&'static str>);
+}
+
+impl IntoIgnoreResult for bool {
+    fn into_ignore_result(self) -> (bool, Option<&'static str>) {
+        (self, None)
+    }
+}
+
+impl IntoIgnoreResult for (bool, &'static str) {
+    fn into_ignore_result(self) -> (bool, Option<&'static str>) {
+        (self.0, Some(self.1))
+    }
+}
+
 /// Macro to generate the main function for the test harness.
 #[macro_export]
 macro_rules! test_main {
@@ -35,6 +56,10 @@ macro_rules! test_main {
 ///   assert_eq!("", "");
 /// }
 /// ```
+///
+/// An optional `should_panic: $expected` clause marks the test as expected to panic (optionally
+/// with a message containing `$expected`, an `Option<&str>`), mirroring `#[should_panic]` on a
+/// plain `#[test]`.
 #[macro_export]
 macro_rules! test {
     ($test_name:ident) => {
@@ -43,19 +68,55 @@ macro_rules! test {
             fn [< __test_ $test_name >]() -> $crate::_libtest_mimic::Trial {
                 $crate::_libtest_mimic::Trial::test(
                     $crate::_prepend_module_path!(::std::stringify!($test_name)),
-                    move || $crate::runner::run($test_name),
+                    move || $crate::runner::run($crate::_module_path!(), $test_name),
                 )
             }
         );
     };
     ($test_name:ident, ignore_if: $ignore_expr:expr) => {
+        $crate::_paste::paste!(
+            #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_TESTS)]
+            fn [< __test_ $test_name >]() -> $crate::_libtest_mimic::Trial {
+                let name = $crate::_prepend_module_path!(::std::stringify!($test_name));
+                let (ignored, reason) = $crate::IntoIgnoreResult::into_ignore_result($ignore_expr);
+                if ignored {
+                    if let Some(reason) = reason {
+                        $crate::runner::record_ignore_reason(&name, reason);
+                    }
+                }
+                $crate::_libtest_mimic::Trial::test(
+                    name,
+                    move || $crate::runner::run($crate::_module_path!(), $test_name),
+                ).with_ignored_flag(ignored)
+            }
+        );
+    };
+    ($test_name:ident, should_panic: $expected:expr) => {
         $crate::_paste::paste!(
             #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_TESTS)]
             fn [< __test_ $test_name >]() -> $crate::_libtest_mimic::Trial {
                 $crate::_libtest_mimic::Trial::test(
                     $crate::_prepend_module_path!(::std::stringify!($test_name)),
-                    move || $crate::runner::run($test_name),
-                ).with_ignored_flag($ignore_expr)
+                    move || $crate::runner::run_should_panic($crate::_module_path!(), $test_name, $expected),
+                )
+            }
+        );
+    };
+    ($test_name:ident, ignore_if: $ignore_expr:expr, should_panic: $expected:expr) => {
+        $crate::_paste::paste!(
+            #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_TESTS)]
+            fn [< __test_ $test_name >]() -> $crate::_libtest_mimic::Trial {
+                let name = $crate::_prepend_module_path!(::std::stringify!($test_name));
+                let (ignored, reason) = $crate::IntoIgnoreResult::into_ignore_result($ignore_expr);
+                if ignored {
+                    if let Some(reason) = reason {
+                        $crate::runner::record_ignore_reason(&name, reason);
+                    }
+                }
+                $crate::_libtest_mimic::Trial::test(
+                    name,
+                    move || $crate::runner::run_should_panic($crate::_module_path!(), $test_name, $expected),
+                ).with_ignored_flag(ignored)
             }
         );
     };
@@ -84,8 +145,49 @@ macro_rules! test {
 ///     assert_eq!(param % 2, 0);
 /// }
 /// ```
+///
+/// # Parameter matrices
+///
+/// Passing several generators inside brackets runs the test over the Cartesian product of all
+/// of them, generating one `Trial` per combination. The wrapped function takes one parameter
+/// per axis, in the order the generators are listed, and `ignore_if` (if present) receives a
+/// reference to the full tuple of values rather than a single value. Each axis's value type must
+/// implement `Clone`, since a value from an outer axis is reused across every combination nested
+/// inside it.
+///
+/// ```
+/// use rdroidtest::ptest;
+///
+/// fn sizes() -> Vec<(String, u32)> {
+///     vec![("4k".to_string(), 4096), ("64k".to_string(), 65536)]
+/// }
+///
+/// fn alignments() -> Vec<(String, u32)> {
+///     vec![("16".to_string(), 16), ("32".to_string(), 32)]
+/// }
+///
+/// ptest!(buffer_is_aligned, [sizes(), alignments()]);
+/// fn buffer_is_aligned(size: u32, alignment: u32) {
+///     assert_eq!(size % alignment, 0);
+/// }
+/// ```
 #[macro_export]
 macro_rules! ptest {
+    // The bracket (Cartesian-product) arms must come before the single-generator `$param_gen:expr`
+    // arms below: an array literal like `[sizes(), alignments()]` is itself a valid `expr`, so if
+    // the single-generator arms came first they would swallow the bracket form too.
+    ($test_name:ident, [$($param_gen:expr),+ $(,)?]) => {
+        $crate::_ptest_cross!(@init $test_name, |_p| false, false, ::std::option::Option::None, $($param_gen),+);
+    };
+    ($test_name:ident, [$($param_gen:expr),+ $(,)?], ignore_if: $ignore_expr:expr) => {
+        $crate::_ptest_cross!(@init $test_name, $ignore_expr, false, ::std::option::Option::None, $($param_gen),+);
+    };
+    ($test_name:ident, [$($param_gen:expr),+ $(,)?], should_panic: $expected:expr) => {
+        $crate::_ptest_cross!(@init $test_name, |_p| false, true, $expected, $($param_gen),+);
+    };
+    ($test_name:ident, [$($param_gen:expr),+ $(,)?], ignore_if: $ignore_expr:expr, should_panic: $expected:expr) => {
+        $crate::_ptest_cross!(@init $test_name, $ignore_expr, true, $expected, $($param_gen),+);
+    };
     ($test_name:ident, $param_gen:expr) => {
         $crate::_paste::paste!(
             #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_PTESTS)]
@@ -97,7 +199,7 @@ macro_rules! ptest {
                             $crate::_prepend_module_path!(::std::stringify!($test_name)),
                             name
                         ),
-                        move || $crate::runner::run(|| $test_name(val)),
+                        move || $crate::runner::run($crate::_module_path!(), || $test_name(val)),
                     )
                 }).collect()
             }
@@ -108,14 +210,61 @@ macro_rules! ptest {
             #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_PTESTS)]
             fn [< __ptest_ $test_name >]() -> Vec<$crate::_libtest_mimic::Trial> {
                 $param_gen.into_iter().map(|(name, val)| {
-                    let ignored = $ignore_expr(&val);
+                    let full_name = format!(
+                        "{}/{}",
+                        $crate::_prepend_module_path!(::std::stringify!($test_name)),
+                        name
+                    );
+                    let (ignored, reason) = $crate::IntoIgnoreResult::into_ignore_result($ignore_expr(&val));
+                    if ignored {
+                        if let Some(reason) = reason {
+                            $crate::runner::record_ignore_reason(&full_name, reason);
+                        }
+                    }
+                    $crate::_libtest_mimic::Trial::test(
+                        full_name,
+                        move || $crate::runner::run($crate::_module_path!(), || $test_name(val)),
+                    ).with_ignored_flag(ignored)
+                }).collect()
+            }
+        );
+    };
+    ($test_name:ident, $param_gen:expr, should_panic: $expected:expr) => {
+        $crate::_paste::paste!(
+            #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_PTESTS)]
+            fn [< __ptest_ $test_name >]() -> Vec<$crate::_libtest_mimic::Trial> {
+                $param_gen.into_iter().map(|(name, val)| {
                     $crate::_libtest_mimic::Trial::test(
                         format!(
                             "{}/{}",
                             $crate::_prepend_module_path!(::std::stringify!($test_name)),
                             name
                         ),
-                        move || $crate::runner::run(|| $test_name(val)),
+                        move || $crate::runner::run_should_panic($crate::_module_path!(), || $test_name(val), $expected),
+                    )
+                }).collect()
+            }
+        );
+    };
+    ($test_name:ident, $param_gen:expr, ignore_if: $ignore_expr:expr, should_panic: $expected:expr) => {
+        $crate::_paste::paste!(
+            #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_PTESTS)]
+            fn [< __ptest_ $test_name >]() -> Vec<$crate::_libtest_mimic::Trial> {
+                $param_gen.into_iter().map(|(name, val)| {
+                    let full_name = format!(
+                        "{}/{}",
+                        $crate::_prepend_module_path!(::std::stringify!($test_name)),
+                        name
+                    );
+                    let (ignored, reason) = $crate::IntoIgnoreResult::into_ignore_result($ignore_expr(&val));
+                    if ignored {
+                        if let Some(reason) = reason {
+                            $crate::runner::record_ignore_reason(&full_name, reason);
+                        }
+                    }
+                    $crate::_libtest_mimic::Trial::test(
+                        full_name,
+                        move || $crate::runner::run_should_panic($crate::_module_path!(), || $test_name(val), $expected),
                     ).with_ignored_flag(ignored)
                 }).collect()
             }
@@ -123,15 +272,146 @@ macro_rules! ptest {
     };
 }
 
+/// Implementation detail of [`ptest!`]'s Cartesian-product form: recursively nests one `for`
+/// loop per `Vec<(String, Ti)>` axis, accumulating each loop's name and value bindings, then
+/// emits one `Trial` per combination in the innermost loop once all axes have been folded in.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _ptest_cross {
+    (@init $test_name:ident, $ignore_expr:expr, $should_panic:tt, $expected:expr, $($param_gen:expr),+) => {
+        $crate::_paste::paste!(
+            #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_PTESTS)]
+            fn [< __ptest_ $test_name >]() -> Vec<$crate::_libtest_mimic::Trial> {
+                let mut trials = Vec::new();
+                $crate::_ptest_cross!(
+                    trials, $test_name, $ignore_expr, $should_panic, $expected, [], [], $($param_gen),+
+                );
+                trials
+            }
+        );
+    };
+    // Base case, expected to panic: every axis has been folded in, so emit the `Trial` for this
+    // combination, running it via `run_should_panic`.
+    ($trials:ident, $test_name:ident, $ignore_expr:expr, true, $expected:expr, [$($name:ident),*], [$($val:ident),*] $(,)?) => {
+        {
+            let full_name = format!(
+                "{}/{}",
+                $crate::_prepend_module_path!(::std::stringify!($test_name)),
+                [$($name.clone()),*].join("/")
+            );
+            // Clone rather than move: the outer axes' values are shared across every combination
+            // of the axes nested inside them, so moving them into `params` here would only be
+            // sound for the innermost axis.
+            let params = ($($val.clone()),*,);
+            let (ignored, reason) = $crate::IntoIgnoreResult::into_ignore_result(($ignore_expr)(&params));
+            if ignored {
+                if let Some(reason) = reason {
+                    $crate::runner::record_ignore_reason(&full_name, reason);
+                }
+            }
+            $trials.push(
+                $crate::_libtest_mimic::Trial::test(full_name, move || {
+                    let ($($val),*,) = params;
+                    $crate::runner::run_should_panic($crate::_module_path!(), move || $test_name($($val),*), $expected)
+                }).with_ignored_flag(ignored)
+            );
+        }
+    };
+    // Base case, not expected to panic: same as above, but via the ordinary `run`.
+    ($trials:ident, $test_name:ident, $ignore_expr:expr, false, $expected:expr, [$($name:ident),*], [$($val:ident),*] $(,)?) => {
+        {
+            let full_name = format!(
+                "{}/{}",
+                $crate::_prepend_module_path!(::std::stringify!($test_name)),
+                [$($name.clone()),*].join("/")
+            );
+            // Clone rather than move: the outer axes' values are shared across every combination
+            // of the axes nested inside them, so moving them into `params` here would only be
+            // sound for the innermost axis.
+            let params = ($($val.clone()),*,);
+            let (ignored, reason) = $crate::IntoIgnoreResult::into_ignore_result(($ignore_expr)(&params));
+            if ignored {
+                if let Some(reason) = reason {
+                    $crate::runner::record_ignore_reason(&full_name, reason);
+                }
+            }
+            $trials.push(
+                $crate::_libtest_mimic::Trial::test(full_name, move || {
+                    let ($($val),*,) = params;
+                    $crate::runner::run($crate::_module_path!(), move || $test_name($($val),*))
+                }).with_ignored_flag(ignored)
+            );
+        }
+    };
+    // Recursive case: loop over the next axis and fold each of its values into the accumulator.
+    ($trials:ident, $test_name:ident, $ignore_expr:expr, $should_panic:tt, $expected:expr, [$($name:ident),*], [$($val:ident),*], $gen:expr $(, $rest:expr)*) => {
+        for (_name, _val) in $gen.into_iter() {
+            $crate::_ptest_cross!(
+                $trials, $test_name, $ignore_expr, $should_panic, $expected,
+                [$($name,)* _name], [$($val,)* _val],
+                $($rest),*
+            );
+        }
+    };
+}
+
+/// Macro to register a setup/teardown fixture for every test in and below the current module.
+///
+/// # Usage
+///
+/// ```
+/// use rdroidtest::{fixture, test};
+///
+/// fn my_fixture() -> Box<dyn FnOnce() + Send> {
+///     // Setup code runs here, before each applicable test.
+///     Box::new(|| {
+///         // Teardown code runs here, after each applicable test (even if it panicked).
+///     })
+/// }
+///
+/// fixture!(my_fixture);
+///
+/// test!(uses_the_fixture);
+/// fn uses_the_fixture() {}
+/// ```
+#[macro_export]
+macro_rules! fixture {
+    ($fixture_name:ident) => {
+        $crate::_paste::paste!(
+            #[$crate::_linkme::distributed_slice($crate::runner::RDROIDTEST_FIXTURES)]
+            // `module_path!()` (crate name and all) rather than `$crate::_module_path!()`: the
+            // latter calls `str::split_once`, which isn't `const` and so can't appear in a
+            // `static` initializer. The crate name is stripped at match time instead, by
+            // `runner::setup_fixtures` (via `runner::strip_crate_name`).
+            static [< __FIXTURE_ $fixture_name >]: (&'static str, fn() -> Box<dyn FnOnce() + Send>) =
+                (module_path!(), $fixture_name);
+        );
+    };
+}
+
 /// Prepends module path (without the crate name) to the test name and returns
 /// the new string.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! _prepend_module_path {
     ($test_name:expr) => {{
-        match module_path!().split_once("::") {
-            Some((_, path)) => format!("{}::{}", path, $test_name),
-            None => format!("{}", $test_name),
+        let path = $crate::_module_path!();
+        if path.is_empty() {
+            format!("{}", $test_name)
+        } else {
+            format!("{}::{}", path, $test_name)
         }
     }};
 }
+
+/// Returns the module path of the invocation site, without the crate name.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! _module_path {
+    () => {
+        match module_path!().split_once("::") {
+            Some((_, path)) => path,
+            None => "",
+        }
+    };
+}
@@ -0,0 +1,248 @@
+//! Runner which collects tests registered by the `test!`/`ptest!` macros and hands them to
+//! `libtest_mimic`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+use libtest_mimic::{Arguments, Failed, Trial};
+use linkme::distributed_slice;
+
+/// Registry of non-parameterized tests, populated by the `test!` macro (and the `rdroidtest`
+/// attribute when used without a parameter generator).
+#[distributed_slice]
+pub static RDROIDTEST_TESTS: [fn() -> Trial] = [..];
+
+/// Registry of parameterized tests, populated by the `ptest!` macro (and the `rdroidtest`
+/// attribute when used with one or more parameter generators).
+#[distributed_slice]
+pub static RDROIDTEST_PTESTS: [fn() -> Vec<Trial>] = [..];
+
+/// Registry of setup/teardown fixtures, populated by the `fixture!` macro (and the
+/// `rdroidtest_fixture` attribute). Each entry is the full `module_path!()` (crate name included)
+/// the fixture was registered in, paired with its setup function; the setup function's return
+/// value is the teardown to run afterwards.
+#[distributed_slice]
+pub static RDROIDTEST_FIXTURES: [(&'static str, fn() -> Box<dyn FnOnce() + Send>)] = [..];
+
+/// Collects all registered trials, in the order their modules were linked.
+fn collect_trials() -> Vec<Trial> {
+    let mut trials: Vec<Trial> = RDROIDTEST_TESTS.iter().map(|f| f()).collect();
+    trials.extend(RDROIDTEST_PTESTS.iter().flat_map(|f| f()));
+    trials
+}
+
+/// Human-readable reasons given via `ignore_if(<expr>, <reason>)`, keyed by the trial's
+/// fully-qualified name. Populated as trials are collected (see `record_ignore_reason`), since
+/// that's the only point at which the `ignore_if` expression is evaluated.
+static IGNORE_REASONS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Records why a test was ignored, for `main` to print alongside the test run. Called by the
+/// `test!`/`ptest!` macros when their `ignore_if` expression evaluates to `(true, reason)`.
+#[doc(hidden)]
+pub fn record_ignore_reason(name: &str, reason: &str) {
+    IGNORE_REASONS.lock().unwrap().push((name.to_string(), reason.to_string()));
+}
+
+/// Entry point used by `rdroidtest::test_main!` to run the full test suite.
+///
+/// Supports `--shard-index N --shard-count M` (or the `RDROIDTEST_SHARD_INDEX` /
+/// `RDROIDTEST_SHARD_COUNT` environment variables, for CI setups that can't touch argv) to run
+/// only the `N`th of `M` shards of the test suite; see [`Shard`].
+pub fn main() {
+    let raw_args: Vec<String> = std::env::args().collect();
+    let (shard, remaining_args) = Shard::extract_from_args(&raw_args);
+
+    let args = Arguments::from_iter(remaining_args);
+    let mut trials = collect_trials();
+    if let Some(shard) = shard {
+        trials = shard.select(trials);
+    }
+    print_ignore_reasons(&trials);
+    libtest_mimic::run(&args, trials).exit();
+}
+
+/// Prints why each ignored trial (still present after sharding) was skipped, for the `ignore_if(
+/// <expr>, <reason>)` trials that recorded one. Run before handing off to `libtest_mimic`, whose
+/// own report doesn't carry reasons.
+///
+/// `pub(crate)` so the `custom_test_framework` backend can reuse it for its own trials.
+pub(crate) fn print_ignore_reasons(trials: &[Trial]) {
+    let reasons = IGNORE_REASONS.lock().unwrap();
+    for (name, reason) in reasons.iter() {
+        if trials.iter().any(|trial| trial.name() == name) {
+            println!("ignored {name}: {reason}");
+        }
+    }
+}
+
+/// Identifies one shard of a test suite split `count` ways, for splitting a suite across
+/// parallel workers (e.g. separate test targets in CI).
+///
+/// `pub(crate)` so the `custom_test_framework` backend can shard its own trials the same way.
+pub(crate) struct Shard {
+    index: u64,
+    count: u64,
+}
+
+impl Shard {
+    /// Parses `--shard-index`/`--shard-count` out of `args` (falling back to the
+    /// `RDROIDTEST_SHARD_INDEX`/`RDROIDTEST_SHARD_COUNT` environment variables if the flags
+    /// aren't present), returning the shard (if any) and the remaining args for
+    /// `libtest_mimic::Arguments` to parse.
+    ///
+    /// Panics if only one of index/count is given, or if `index >= count`.
+    pub(crate) fn extract_from_args(args: &[String]) -> (Option<Shard>, Vec<String>) {
+        let mut index = None;
+        let mut count = None;
+        let mut remaining = Vec::with_capacity(args.len());
+
+        let mut iter = args.iter().cloned();
+        while let Some(arg) = iter.next() {
+            match arg.split_once('=') {
+                Some(("--shard-index", value)) => index = Some(parse_shard_arg("--shard-index", value)),
+                Some(("--shard-count", value)) => count = Some(parse_shard_arg("--shard-count", value)),
+                _ if arg == "--shard-index" => {
+                    let value = iter.next().expect("--shard-index requires a value");
+                    index = Some(parse_shard_arg("--shard-index", &value));
+                }
+                _ if arg == "--shard-count" => {
+                    let value = iter.next().expect("--shard-count requires a value");
+                    count = Some(parse_shard_arg("--shard-count", &value));
+                }
+                _ => remaining.push(arg),
+            }
+        }
+
+        if index.is_none() && count.is_none() {
+            index = std::env::var("RDROIDTEST_SHARD_INDEX").ok().map(|v| parse_shard_arg("RDROIDTEST_SHARD_INDEX", &v));
+            count = std::env::var("RDROIDTEST_SHARD_COUNT").ok().map(|v| parse_shard_arg("RDROIDTEST_SHARD_COUNT", &v));
+        }
+
+        let shard = match (index, count) {
+            (None, None) => None,
+            (Some(index), Some(count)) => {
+                assert!(index < count, "--shard-index ({index}) must be less than --shard-count ({count})");
+                Some(Shard { index, count })
+            }
+            _ => panic!("--shard-index and --shard-count must be given together"),
+        };
+        (shard, remaining)
+    }
+
+    /// Returns the subset of `trials` assigned to this shard.
+    ///
+    /// Partitions by a stable hash of each trial's fully-qualified name, modulo the shard count,
+    /// rather than by list position, so that adding or removing one test doesn't reshuffle every
+    /// other test between shards.
+    pub(crate) fn select(&self, trials: Vec<Trial>) -> Vec<Trial> {
+        trials.into_iter().filter(|trial| hash_name(trial.name()) % self.count == self.index).collect()
+    }
+}
+
+fn parse_shard_arg(flag: &str, value: &str) -> u64 {
+    value.parse().unwrap_or_else(|_| panic!("{flag} expects a non-negative integer, got {value:?}"))
+}
+
+/// Stable (not randomly-seeded) hash of a trial name, used to deterministically assign trials to
+/// shards.
+fn hash_name(name: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Runs a single test closure, converting a panic into a `libtest_mimic::Failed` so it is
+/// reported as a normal test failure rather than aborting the harness.
+///
+/// `module_path` is the test's module path (without the crate name), used to select which
+/// registered fixtures apply to it.
+pub fn run<F: FnOnce() + std::panic::UnwindSafe>(module_path: &str, f: F) -> Result<(), Failed> {
+    let _teardown = setup_fixtures(module_path);
+    std::panic::catch_unwind(f).map_err(|e| Failed::from(panic_message(&*e)))
+}
+
+/// Runs a test closure that is expected to panic, for `#[should_panic]`/`#[should_panic(expected
+/// = "...")]`. Fails the test if the closure does not panic, or if `expected` is given and the
+/// panic message doesn't contain it; succeeds otherwise.
+///
+/// `module_path` is the test's module path (without the crate name), used to select which
+/// registered fixtures apply to it.
+pub fn run_should_panic<F: FnOnce() + std::panic::UnwindSafe>(
+    module_path: &str,
+    f: F,
+    expected: Option<&str>,
+) -> Result<(), Failed> {
+    let _teardown = setup_fixtures(module_path);
+
+    // Note: unlike some `should_panic` implementations, this doesn't suppress the default panic
+    // hook's stderr output. Doing so would mean swapping the process-global panic hook, which
+    // races with other trials' `run`/`run_should_panic` calls under libtest-mimic's default
+    // parallel execution.
+    match std::panic::catch_unwind(f) {
+        Ok(()) => Err(Failed::from("test did not panic as expected")),
+        Err(e) => {
+            let message = panic_message(&*e);
+            match expected {
+                Some(expected) if !message.contains(expected) => Err(Failed::from(format!(
+                    "test panicked with {message:?}, but the panic message did not contain {expected:?}"
+                ))),
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+/// Runs the setup function of every fixture applicable to `module_path`, returning a guard that
+/// runs their teardowns (in reverse registration order) when dropped -- including when a test
+/// panics, since `run`/`run_should_panic` hold the guard across their `catch_unwind` call.
+fn setup_fixtures(module_path: &str) -> FixtureTeardownGuard {
+    let teardowns = RDROIDTEST_FIXTURES
+        .iter()
+        .filter(|(fixture_module, _)| fixture_applies(strip_crate_name(fixture_module), module_path))
+        .map(|(_, setup)| setup())
+        .collect();
+    FixtureTeardownGuard(teardowns)
+}
+
+/// Strips the leading crate name off a full `module_path!()` string, to match the module paths
+/// `run`/`run_should_panic` are given (which are already crate-name-less, via
+/// `rdroidtest::_module_path!()`).
+fn strip_crate_name(full_module_path: &str) -> &str {
+    match full_module_path.split_once("::") {
+        Some((_, path)) => path,
+        None => "",
+    }
+}
+
+/// Whether a fixture registered at `fixture_module` applies to a test at `test_module`: the
+/// fixture's module is the test's module, or a (possibly indirect) parent of it.
+fn fixture_applies(fixture_module: &str, test_module: &str) -> bool {
+    fixture_module.is_empty()
+        || test_module == fixture_module
+        || test_module.starts_with(&format!("{fixture_module}::"))
+}
+
+/// Runs its held teardown closures, in reverse order, on drop.
+struct FixtureTeardownGuard(Vec<Box<dyn FnOnce() + Send>>);
+
+impl Drop for FixtureTeardownGuard {
+    fn drop(&mut self) {
+        for teardown in self.0.drain(..).rev() {
+            teardown();
+        }
+    }
+}
+
+/// Extracts a human-readable message from a `std::panic::catch_unwind` payload.
+///
+/// `pub(crate)` so the `custom_test_framework` backend can report panics the same way.
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "test panicked with a non-string payload".to_string()
+    }
+}
@@ -1,6 +1,8 @@
 //! Test use of `rdroidtest` attribute macro.
 
-use rdroidtest::{ignore_if, rdroidtest};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rdroidtest::{ignore_if, rdroidtest, rdroidtest_fixture};
 
 mod raw;
 
@@ -83,6 +85,19 @@ fn more_instances() -> Vec<(String, String)> {
     vec![("one".to_string(), "one".to_string()), ("two".to_string(), "two".to_string())]
 }
 
+#[rdroidtest]
+#[ignore_if(feeling_happy(), "not grumpy today")]
+fn grumble_with_reason() {
+    let result = 1 + 1;
+    assert_eq!(result, 2);
+}
+
+#[rdroidtest(my_instances())]
+#[ignore_if(|p| (feeling_odd(p), "odd numbers aren't even"))]
+fn is_even_with_reason(param: u32) {
+    assert_eq!(param % 2, 0);
+}
+
 #[rdroidtest]
 #[ignore]
 fn ignore_me() {
@@ -109,4 +124,79 @@ fn ignore_all(param: u32) {
     panic!("parameterized test ({param}) shouldn't run");
 }
 
+// Cross-product parameter matrix: one `Trial` per (size, alignment) combination.
+
+#[rdroidtest(sizes(), alignments())]
+fn size_is_multiple_of_alignment(size: u32, alignment: u32) {
+    assert_eq!(size % alignment, 0);
+}
+
+#[rdroidtest(sizes(), alignments())]
+#[ignore_if(|p: &(u32, u32)| p.0 <= p.1)]
+fn size_exceeds_alignment(size: u32, alignment: u32) {
+    assert!(size > alignment);
+}
+
+#[rdroidtest(sizes(), alignments())]
+#[ignore_if(|p: &(u32, u32)| (p.0 <= p.1, "size does not exceed alignment"))]
+fn size_exceeds_alignment_with_reason(size: u32, alignment: u32) {
+    assert!(size > alignment);
+}
+
+fn sizes() -> Vec<(String, u32)> {
+    vec![("4k".to_string(), 4096), ("64k".to_string(), 65536)]
+}
+
+fn alignments() -> Vec<(String, u32)> {
+    vec![("16".to_string(), 16), ("32".to_string(), 32)]
+}
+
+#[rdroidtest]
+#[should_panic]
+fn expected_to_panic() {
+    panic!("this is expected");
+}
+
+#[rdroidtest]
+#[should_panic(expected = "specific message")]
+fn expected_to_panic_with_message() {
+    panic!("this has a specific message");
+}
+
+#[rdroidtest(my_instances())]
+#[should_panic(expected = "too small")]
+fn is_too_small(param: u32) {
+    assert!(param >= 5, "too small");
+}
+
+mod with_fixture {
+    use super::*;
+
+    static SET_UP: AtomicUsize = AtomicUsize::new(0);
+    static TORN_DOWN: AtomicUsize = AtomicUsize::new(0);
+
+    #[rdroidtest_fixture]
+    fn counting_fixture() -> Box<dyn FnOnce() + Send> {
+        SET_UP.fetch_add(1, Ordering::SeqCst);
+        Box::new(|| {
+            TORN_DOWN.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    #[rdroidtest]
+    fn fixture_has_run_before_the_test() {
+        // Not `SET_UP == TORN_DOWN + 1`: trials run concurrently, so another test in this
+        // module may have set up (or torn down) its own run of the fixture in the meantime. All
+        // we can assert is that this test's own setup -- which ran and hasn't yet torn down --
+        // keeps `SET_UP` ahead of `TORN_DOWN`.
+        assert!(SET_UP.load(Ordering::SeqCst) > TORN_DOWN.load(Ordering::SeqCst));
+    }
+
+    #[rdroidtest]
+    #[should_panic]
+    fn teardown_still_runs_if_the_test_panics() {
+        panic!("the fixture's teardown should still run after this");
+    }
+}
+
 rdroidtest::test_main!();
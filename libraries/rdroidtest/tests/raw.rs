@@ -1,6 +1,8 @@
 //! Test use of `rdroidtest`.
 
-use rdroidtest::{ptest, test};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rdroidtest::{fixture, ptest, test};
 
 // Tests using raw declarative macros.
 
@@ -26,6 +28,12 @@ fn feeling_happy() -> bool {
     false
 }
 
+test!(grumble_with_reason, ignore_if: (feeling_happy(), "not grumpy today"));
+fn grumble_with_reason() {
+    let result = 1 + 1;
+    assert_eq!(result, 2);
+}
+
 ptest!(is_less_than_five, my_instances());
 fn is_less_than_five(param: u32) {
     assert!(param < 5);
@@ -45,6 +53,11 @@ fn feeling_odd(param: &u32) -> bool {
     *param % 2 == 1
 }
 
+ptest!(is_even_with_reason, my_instances(), ignore_if: |p| (feeling_odd(p), "odd numbers aren't even"));
+fn is_even_with_reason(param: u32) {
+    assert_eq!(param % 2, 0);
+}
+
 fn my_instances() -> Vec<(String, u32)> {
     vec![("one".to_string(), 1), ("two".to_string(), 2), ("three".to_string(), 3)]
 }
@@ -76,3 +89,81 @@ fn is_the_one(param: String) {
 fn more_instances() -> Vec<(String, String)> {
     vec![("one".to_string(), "one".to_string()), ("two".to_string(), "two".to_string())]
 }
+
+// Cross-product parameter matrix: one `Trial` per (size, alignment) combination.
+
+ptest!(size_is_multiple_of_alignment, [sizes(), alignments()]);
+fn size_is_multiple_of_alignment(size: u32, alignment: u32) {
+    assert_eq!(size % alignment, 0);
+}
+
+ptest!(
+    size_exceeds_alignment,
+    [sizes(), alignments()],
+    ignore_if: |p: &(u32, u32)| p.0 <= p.1
+);
+fn size_exceeds_alignment(size: u32, alignment: u32) {
+    assert!(size > alignment);
+}
+
+ptest!(
+    size_exceeds_alignment_with_reason,
+    [sizes(), alignments()],
+    ignore_if: |p: &(u32, u32)| (p.0 <= p.1, "size does not exceed alignment")
+);
+fn size_exceeds_alignment_with_reason(size: u32, alignment: u32) {
+    assert!(size > alignment);
+}
+
+fn sizes() -> Vec<(String, u32)> {
+    vec![("4k".to_string(), 4096), ("64k".to_string(), 65536)]
+}
+
+fn alignments() -> Vec<(String, u32)> {
+    vec![("16".to_string(), 16), ("32".to_string(), 32)]
+}
+
+test!(expected_to_panic, should_panic: None);
+fn expected_to_panic() {
+    panic!("this is expected");
+}
+
+test!(expected_to_panic_with_message, should_panic: Some("specific message"));
+fn expected_to_panic_with_message() {
+    panic!("this has a specific message");
+}
+
+ptest!(is_too_small, my_instances(), should_panic: Some("too small"));
+fn is_too_small(param: u32) {
+    assert!(param >= 5, "too small");
+}
+
+mod with_fixture {
+    use super::*;
+
+    static SET_UP: AtomicUsize = AtomicUsize::new(0);
+    static TORN_DOWN: AtomicUsize = AtomicUsize::new(0);
+
+    fn counting_fixture() -> Box<dyn FnOnce() + Send> {
+        SET_UP.fetch_add(1, Ordering::SeqCst);
+        Box::new(|| {
+            TORN_DOWN.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    fixture!(counting_fixture);
+
+    test!(fixture_has_run_before_the_test);
+    fn fixture_has_run_before_the_test() {
+        // Not `SET_UP == TORN_DOWN + 1`: trials run concurrently, so another test in this
+        // module may have set up (or torn down) its own run of the fixture in the meantime. All
+        // we can assert is that this test's own setup -- which ran and hasn't yet torn down --
+        // keeps `SET_UP` ahead of `TORN_DOWN`.
+        assert!(SET_UP.load(Ordering::SeqCst) > TORN_DOWN.load(Ordering::SeqCst));
+    }
+
+    test!(teardown_still_runs_if_the_test_panics, should_panic: None);
+    fn teardown_still_runs_if_the_test_panics() {
+        panic!("the fixture's teardown should still run after this");
+    }
+}